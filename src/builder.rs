@@ -0,0 +1,161 @@
+//! Builder for [`Generator`].
+
+use std::collections::{BinaryHeap, HashSet};
+use std::time::Duration;
+
+use rand::{rngs::StdRng, SeedableRng};
+use slot_clock::{SlotClock, SystemTimeSlotClock};
+use tokio::time::sleep;
+
+use crate::metrics::MetricsRecorder;
+use crate::sizes::DEFAULT_MAX_BLOBS_PER_BLOCK;
+use crate::slot_generator::{SlotGenerator, ValId};
+use crate::{Generator, PublishOffsets};
+
+/// Default slot duration, matching mainnet.
+const DEFAULT_SLOT_DURATION: Duration = Duration::from_secs(12);
+
+/// Builds a [`Generator`].
+#[derive(Default)]
+pub struct GeneratorBuilder {
+    genesis_time: Option<Duration>,
+    slot_duration: Option<Duration>,
+    validator_count: Option<u64>,
+    validators: HashSet<ValId>,
+    publish_offsets: Option<PublishOffsets>,
+    gossip_clock_disparity: Option<Duration>,
+    rng_seed: Option<u64>,
+    max_blobs_per_block: Option<u64>,
+    distributed: bool,
+    force_light_client_finality_updates: bool,
+    metrics: Option<Box<dyn MetricsRecorder>>,
+}
+
+impl GeneratorBuilder {
+    /// Sets the genesis time, as a duration since the `UNIX_EPOCH`.
+    pub fn genesis_time(mut self, genesis_time: Duration) -> Self {
+        self.genesis_time = Some(genesis_time);
+        self
+    }
+
+    /// Sets the slot duration. Defaults to 12 seconds.
+    pub fn slot_duration(mut self, slot_duration: Duration) -> Self {
+        self.slot_duration = Some(slot_duration);
+        self
+    }
+
+    /// Sets the total number of validators in the simulated network.
+    pub fn validator_count(mut self, validator_count: u64) -> Self {
+        self.validator_count = Some(validator_count);
+        self
+    }
+
+    /// Sets the validators managed by this node.
+    pub fn validators(mut self, validators: HashSet<ValId>) -> Self {
+        self.validators = validators;
+        self
+    }
+
+    /// Sets the per-`MsgType` offsets, relative to the start of a slot, at
+    /// which messages are released. Defaults to [`PublishOffsets::spec_default`].
+    pub fn publish_offsets(mut self, publish_offsets: PublishOffsets) -> Self {
+        self.publish_offsets = Some(publish_offsets);
+        self
+    }
+
+    /// Enables gossip clock-disparity jitter: each message's release instant
+    /// is perturbed by a uniformly-sampled offset in `[-disparity, +disparity]`.
+    /// Disabled by default. Pass [`crate::MAXIMUM_GOSSIP_CLOCK_DISPARITY`] to
+    /// match the window real clients tolerate.
+    pub fn gossip_clock_disparity(mut self, disparity: Duration) -> Self {
+        self.gossip_clock_disparity = Some(disparity);
+        self
+    }
+
+    /// Seeds the RNG used for gossip clock-disparity jitter, so that runs
+    /// are reproducible. Defaults to a seed drawn from entropy.
+    pub fn rng_seed(mut self, seed: u64) -> Self {
+        self.rng_seed = Some(seed);
+        self
+    }
+
+    /// Sets the maximum number of blob sidecars generated per block.
+    /// Defaults to [`DEFAULT_MAX_BLOBS_PER_BLOCK`].
+    pub fn max_blobs_per_block(mut self, max_blobs_per_block: u64) -> Self {
+        self.max_blobs_per_block = Some(max_blobs_per_block);
+        self
+    }
+
+    /// Sets whether this node's validators operate inside a distributed
+    /// validator (DVT) cluster. In distributed mode, aggregation and sync
+    /// committee contribution duties are produced for every assigned
+    /// validator each slot rather than only the ones whose selection proof
+    /// crosses the aggregator threshold, and sync committee contributions
+    /// are computed a slot ahead. Defaults to `false`.
+    pub fn distributed(mut self, distributed: bool) -> Self {
+        self.distributed = distributed;
+        self
+    }
+
+    /// Sets whether light client finality updates are emitted every epoch
+    /// boundary regardless of whether this node owns that slot's proposer.
+    /// Defaults to `false` (only emitted by the proposing node).
+    pub fn force_light_client_finality_updates(mut self, force: bool) -> Self {
+        self.force_light_client_finality_updates = force;
+        self
+    }
+
+    /// Enables generation metrics, recorded through the given
+    /// [`MetricsRecorder`]: a counter per [`crate::MsgType`] produced, and a
+    /// histogram of the delay between a message's ideal slot-relative
+    /// publish time and the instant it was actually yielded. Disabled by
+    /// default.
+    pub fn with_metrics(mut self, metrics: impl MetricsRecorder + 'static) -> Self {
+        self.metrics = Some(Box::new(metrics));
+        self
+    }
+
+    /// Builds the [`Generator`].
+    ///
+    /// # Panics
+    ///
+    /// Panics if `genesis_time` or `validator_count` were not set, or if
+    /// called outside a Tokio runtime (the slot timer is created here).
+    pub fn build(self) -> Generator {
+        let genesis_time = self.genesis_time.expect("genesis_time is required");
+        let slot_duration = self.slot_duration.unwrap_or(DEFAULT_SLOT_DURATION);
+        let validator_count = self.validator_count.expect("validator_count is required");
+
+        let slot_clock = SystemTimeSlotClock::new(
+            slot_clock::Slot::new(0),
+            genesis_time,
+            slot_duration,
+        );
+        let duration_to_next_slot = slot_clock
+            .duration_to_next_slot()
+            .unwrap_or(slot_duration);
+
+        Generator {
+            slot_clock,
+            slot_generator: SlotGenerator::new(validator_count, self.distributed),
+            validators: self.validators,
+            queued_messages: BinaryHeap::new(),
+            next_slot: Box::pin(sleep(duration_to_next_slot)),
+            next_message: None,
+            publish_offsets: self
+                .publish_offsets
+                .unwrap_or_else(|| PublishOffsets::spec_default(slot_duration)),
+            gossip_clock_disparity: self.gossip_clock_disparity,
+            rng: self
+                .rng_seed
+                .map(StdRng::seed_from_u64)
+                .unwrap_or_else(StdRng::from_entropy),
+            max_blobs_per_block: self
+                .max_blobs_per_block
+                .unwrap_or(DEFAULT_MAX_BLOBS_PER_BLOCK),
+            distributed: self.distributed,
+            force_light_client_finality_updates: self.force_light_client_finality_updates,
+            metrics: self.metrics,
+        }
+    }
+}
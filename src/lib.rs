@@ -1,29 +1,45 @@
 use std::{
-    collections::{HashSet, VecDeque},
+    cmp::Ordering,
+    collections::{BinaryHeap, HashSet},
     pin::Pin,
     task::Poll,
+    time::Duration,
 };
 
 use futures::{stream::Stream, Future};
+use rand::{rngs::StdRng, Rng};
 use slot_clock::{Slot, SlotClock, SystemTimeSlotClock};
 use slot_generator::{SlotGenerator, Subnet, ValId};
+use std::cmp::Reverse;
 use strum::{EnumIter, IntoEnumIterator};
-use tokio::time::{sleep, Sleep};
+use tokio::time::{sleep, sleep_until, Instant, Sleep};
+
+/// Clock disparity window tolerated by real clients (500ms). Jitter is off
+/// unless a window is passed to
+/// [`builder::GeneratorBuilder::gossip_clock_disparity`]; this constant is
+/// not applied automatically.
+pub const MAXIMUM_GOSSIP_CLOCK_DISPARITY: Duration = Duration::from_millis(500);
 
 pub mod builder;
+pub mod metrics;
 pub mod sizes;
 pub mod slot_generator;
 #[cfg(test)]
 mod tests;
 
+use metrics::MetricsRecorder;
+
 #[derive(EnumIter, Debug, strum::Display, Clone, Copy)]
 #[strum(serialize_all = "kebab_case")]
 pub enum MsgType {
     BeaconBlock,
+    BlobSidecar,
     AggregateAndProofAttestation,
     Attestation,
     SignedContributionAndProof,
     SyncCommitteeMessage,
+    LightClientFinalityUpdate,
+    LightClientOptimisticUpdate,
 }
 
 pub struct Generator {
@@ -33,10 +49,116 @@ pub struct Generator {
     slot_generator: SlotGenerator,
     /// Validator managed by this node.
     validators: HashSet<ValId>,
-    /// Messages pending to be returned.
-    queued_messages: VecDeque<Message>,
+    /// Messages pending to be returned, ordered by their release instant.
+    queued_messages: BinaryHeap<Reverse<ScheduledMessage>>,
     /// Duration to the next slot.
     next_slot: Pin<Box<Sleep>>,
+    /// Timer armed for the next pending message's release instant, if any.
+    next_message: Option<Pin<Box<Sleep>>>,
+    /// Offsets, relative to the start of a slot, at which each `MsgType` is
+    /// released.
+    publish_offsets: PublishOffsets,
+    /// When set, each message's release instant is perturbed by a uniformly
+    /// sampled offset in `[-disparity, +disparity]`.
+    gossip_clock_disparity: Option<Duration>,
+    /// Seedable source of randomness for [`Generator::gossip_clock_disparity`]
+    /// jitter, so that runs are reproducible.
+    rng: StdRng,
+    /// Maximum number of blob sidecars generated per block.
+    max_blobs_per_block: u64,
+    /// Whether this node's validators operate inside a distributed validator
+    /// (DVT) cluster; see [`builder::GeneratorBuilder::distributed`].
+    distributed: bool,
+    /// When `true`, light client finality updates are emitted every epoch
+    /// boundary regardless of whether this node owns that slot's proposer.
+    force_light_client_finality_updates: bool,
+    /// Optional recorder for generation metrics; see
+    /// [`builder::GeneratorBuilder::with_metrics`].
+    metrics: Option<Box<dyn MetricsRecorder>>,
+}
+
+/// A [`Message`] paired with the instant at which it should be released from
+/// [`Generator::poll_next`].
+struct ScheduledMessage {
+    /// The instant at which this message is actually released, perturbed by
+    /// [`Generator::gossip_clock_disparity`] if configured. Used to order
+    /// and poll the schedule.
+    release: Instant,
+    /// The message's ideal, un-jittered publish instant (`slot_start` plus
+    /// its `MsgType`'s [`PublishOffsets`]), used to measure publish delay
+    /// independent of any later jitter or of how stale the queue has grown.
+    ideal_release: Instant,
+    message: Message,
+}
+
+impl PartialEq for ScheduledMessage {
+    fn eq(&self, other: &Self) -> bool {
+        self.release == other.release
+    }
+}
+
+impl Eq for ScheduledMessage {}
+
+impl PartialOrd for ScheduledMessage {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Ord for ScheduledMessage {
+    fn cmp(&self, other: &Self) -> Ordering {
+        self.release.cmp(&other.release)
+    }
+}
+
+/// Offsets, relative to the start of a slot, at which each message type is
+/// released by [`Generator::poll_next`].
+///
+/// Real validators do not publish every duty at the start of the slot:
+/// blocks are published immediately, attestations and sync committee
+/// messages at a third of the way through the slot, and aggregates at two
+/// thirds of the way through.
+#[derive(Debug, Clone, Copy)]
+pub struct PublishOffsets {
+    pub beacon_block: Duration,
+    pub blob_sidecar: Duration,
+    pub attestation: Duration,
+    pub sync_committee_message: Duration,
+    pub aggregate_and_proof_attestation: Duration,
+    pub signed_contribution_and_proof: Duration,
+    pub light_client_finality_update: Duration,
+    pub light_client_optimistic_update: Duration,
+}
+
+impl PublishOffsets {
+    /// Offsets matching the publication schedule real validators follow.
+    pub fn spec_default(slot_duration: Duration) -> Self {
+        Self {
+            beacon_block: Duration::ZERO,
+            // Blob sidecars are published alongside the block they accompany.
+            blob_sidecar: Duration::ZERO,
+            attestation: slot_duration / 3,
+            sync_committee_message: slot_duration / 3,
+            aggregate_and_proof_attestation: 2 * slot_duration / 3,
+            signed_contribution_and_proof: 2 * slot_duration / 3,
+            // Published once the head block has been processed, alongside it.
+            light_client_finality_update: Duration::ZERO,
+            light_client_optimistic_update: Duration::ZERO,
+        }
+    }
+
+    fn offset(&self, msg_type: MsgType) -> Duration {
+        match msg_type {
+            MsgType::BeaconBlock => self.beacon_block,
+            MsgType::BlobSidecar => self.blob_sidecar,
+            MsgType::Attestation => self.attestation,
+            MsgType::SyncCommitteeMessage => self.sync_committee_message,
+            MsgType::AggregateAndProofAttestation => self.aggregate_and_proof_attestation,
+            MsgType::SignedContributionAndProof => self.signed_contribution_and_proof,
+            MsgType::LightClientFinalityUpdate => self.light_client_finality_update,
+            MsgType::LightClientOptimisticUpdate => self.light_client_optimistic_update,
+        }
+    }
 }
 
 #[derive(Clone, Hash, PartialEq, Eq)]
@@ -45,6 +167,12 @@ pub enum Message {
         proposer: ValId,
         slot: Slot,
     },
+    BlobSidecar {
+        proposer: ValId,
+        index: u64,
+        subnet: Subnet,
+        slot: Slot,
+    },
     AggregateAndProofAttestation {
         aggregator: ValId,
         subnet: Subnet,
@@ -65,6 +193,29 @@ pub enum Message {
         subnet: Subnet,
         slot: Slot,
     },
+    LightClientFinalityUpdate {
+        slot: Slot,
+    },
+    LightClientOptimisticUpdate {
+        slot: Slot,
+    },
+}
+
+impl Message {
+    fn msg_type(&self) -> MsgType {
+        match self {
+            Message::BeaconBlock { .. } => MsgType::BeaconBlock,
+            Message::BlobSidecar { .. } => MsgType::BlobSidecar,
+            Message::AggregateAndProofAttestation { .. } => {
+                MsgType::AggregateAndProofAttestation
+            }
+            Message::Attestation { .. } => MsgType::Attestation,
+            Message::SignedContributionAndProof { .. } => MsgType::SignedContributionAndProof,
+            Message::SyncCommitteeMessage { .. } => MsgType::SyncCommitteeMessage,
+            Message::LightClientFinalityUpdate { .. } => MsgType::LightClientFinalityUpdate,
+            Message::LightClientOptimisticUpdate { .. } => MsgType::LightClientOptimisticUpdate,
+        }
+    }
 }
 
 const EPOCHS_PER_SYNC_COMMITTEE_PERIOD: u64 = 256;
@@ -83,19 +234,42 @@ impl Generator {
         )
     }
 
-    fn queue_slot_msgs(&mut self, current_slot: Slot) {
+    /// Generates this slot's messages and schedules each for release at
+    /// `slot_start` plus its `MsgType`'s publish offset, perturbed by
+    /// [`Generator::gossip_clock_disparity`] if configured.
+    fn queue_slot_msgs(&mut self, current_slot: Slot, slot_start: Instant) {
         for msg_type in MsgType::iter() {
+            let release = slot_start + self.publish_offsets.offset(msg_type);
             match msg_type {
-                MsgType::BeaconBlock => self.queued_messages.extend(
+                MsgType::BeaconBlock => self.schedule(
+                    slot_start,
+                    release,
                     self.slot_generator
                         .get_blocks(current_slot, &self.validators)
                         .into_iter()
                         .map(|proposer| Message::BeaconBlock {
                             proposer,
                             slot: current_slot,
-                        }),
+                        })
+                        .collect::<Vec<_>>(),
+                ),
+                MsgType::BlobSidecar => self.schedule(
+                    slot_start,
+                    release,
+                    self.slot_generator
+                        .get_blob_sidecars(current_slot, &self.validators, self.max_blobs_per_block)
+                        .into_iter()
+                        .map(|(proposer, index, subnet)| Message::BlobSidecar {
+                            proposer,
+                            index,
+                            subnet,
+                            slot: current_slot,
+                        })
+                        .collect::<Vec<_>>(),
                 ),
-                MsgType::AggregateAndProofAttestation => self.queued_messages.extend(
+                MsgType::AggregateAndProofAttestation => self.schedule(
+                    slot_start,
+                    release,
                     self.slot_generator
                         .get_aggregates(current_slot, &self.validators)
                         .map(
@@ -104,38 +278,140 @@ impl Generator {
                                 subnet,
                                 slot: current_slot,
                             },
-                        ),
+                        )
+                        .collect::<Vec<_>>(),
                 ),
-                MsgType::Attestation => self.queued_messages.extend(
+                MsgType::Attestation => self.schedule(
+                    slot_start,
+                    release,
                     self.slot_generator
                         .get_attestations(current_slot, &self.validators)
                         .map(|(attester, subnet)| Message::Attestation {
                             attester,
                             subnet,
                             slot: current_slot,
-                        }),
-                ),
-                MsgType::SignedContributionAndProof => self.queued_messages.extend(
-                    self.slot_generator
-                        .get_sync_committee_aggregates(current_slot, &self.validators)
-                        .map(|(validator, subnet)| Message::SignedContributionAndProof {
-                            validator,
-                            subnet,
-                            slot: current_slot,
-                        }),
+                        })
+                        .collect::<Vec<_>>(),
                 ),
-                MsgType::SyncCommitteeMessage => self.queued_messages.extend(
+                MsgType::SignedContributionAndProof => {
+                    // Distributed clients must pre-compute sync committee
+                    // aggregate selection a slot ahead.
+                    let committee_slot = if self.distributed {
+                        current_slot + 1
+                    } else {
+                        current_slot
+                    };
+                    self.schedule(
+                        slot_start,
+                        release,
+                        self.slot_generator
+                            .get_sync_committee_aggregates(committee_slot, &self.validators)
+                            .map(|(validator, subnet)| Message::SignedContributionAndProof {
+                                validator,
+                                subnet,
+                                slot: committee_slot,
+                            })
+                            .collect::<Vec<_>>(),
+                    )
+                }
+                MsgType::SyncCommitteeMessage => self.schedule(
+                    slot_start,
+                    release,
                     self.slot_generator
                         .get_sync_committee_messages(current_slot, &self.validators)
                         .map(|(validator, subnet)| Message::SyncCommitteeMessage {
                             validator,
                             subnet,
                             slot: current_slot,
-                        }),
+                        })
+                        .collect::<Vec<_>>(),
+                ),
+                MsgType::LightClientOptimisticUpdate => self.schedule(
+                    slot_start,
+                    release,
+                    self.slot_generator
+                        .should_emit_light_client_optimistic_update(current_slot)
+                        .then(|| Message::LightClientOptimisticUpdate { slot: current_slot })
+                        .into_iter()
+                        .collect::<Vec<_>>(),
+                ),
+                MsgType::LightClientFinalityUpdate => self.schedule(
+                    slot_start,
+                    release,
+                    self.slot_generator
+                        .should_emit_light_client_finality_update(
+                            current_slot,
+                            &self.validators,
+                            self.force_light_client_finality_updates,
+                        )
+                        .then(|| Message::LightClientFinalityUpdate { slot: current_slot })
+                        .into_iter()
+                        .collect::<Vec<_>>(),
                 ),
             }
         }
     }
+
+    /// Pushes `messages` onto the schedule, each released at `release`
+    /// perturbed by [`Generator::gossip_clock_disparity`] if configured.
+    fn schedule(&mut self, slot_start: Instant, release: Instant, messages: Vec<Message>) {
+        for message in messages {
+            let jittered = self.jitter(slot_start, release);
+            self.queued_messages.push(Reverse(ScheduledMessage {
+                release: jittered,
+                ideal_release: release,
+                message,
+            }));
+        }
+    }
+
+    /// Perturbs `release` by a uniformly-sampled offset in
+    /// `[-disparity, +disparity]`, never moving it earlier than `slot_start`
+    /// minus the disparity (i.e. the previous slot's clock boundary minus
+    /// the disparity window), so messages stay within the window gossip
+    /// consumers treat as "valid at time of receipt".
+    fn jitter(&mut self, slot_start: Instant, release: Instant) -> Instant {
+        let Some(disparity) = self.gossip_clock_disparity else {
+            return release;
+        };
+
+        let max_offset_millis = disparity.as_millis() as i64;
+        let offset_millis = self.rng.gen_range(-max_offset_millis..=max_offset_millis);
+
+        let jittered = if offset_millis >= 0 {
+            release + Duration::from_millis(offset_millis as u64)
+        } else {
+            release
+                .checked_sub(Duration::from_millis((-offset_millis) as u64))
+                .unwrap_or(release)
+        };
+
+        let floor = slot_start.checked_sub(disparity).unwrap_or(slot_start);
+        jittered.max(floor)
+    }
+
+    /// Records `scheduled`'s release against the configured
+    /// [`metrics::MetricsRecorder`], if any: a count for its `MsgType`, and
+    /// the delay between its own ideal publish instant (`scheduled.ideal_release`)
+    /// and now. A negative delay means the message was yielded early (e.g.
+    /// gossip clock-disparity jitter); a positive one means the consumer fell
+    /// behind and is only now draining a message that was ready earlier.
+    fn record_release(&self, scheduled: &ScheduledMessage) {
+        let Some(metrics) = self.metrics.as_deref() else {
+            return;
+        };
+
+        let msg_type = scheduled.message.msg_type();
+        metrics.inc_message_count(msg_type);
+
+        let now = Instant::now();
+        let delay_seconds = if now >= scheduled.ideal_release {
+            (now - scheduled.ideal_release).as_secs_f64()
+        } else {
+            -(scheduled.ideal_release - now).as_secs_f64()
+        };
+        metrics.observe_publish_delay(msg_type, delay_seconds);
+    }
 }
 
 impl Stream for Generator {
@@ -145,21 +421,48 @@ impl Stream for Generator {
         mut self: Pin<&mut Self>,
         cx: &mut std::task::Context<'_>,
     ) -> Poll<Option<Self::Item>> {
-        // If there were any messages remaining from the current slot, return them.
-        if let Some(msg) = self.queued_messages.pop_front() {
-            return Poll::Ready(Some(msg));
-        }
+        loop {
+            // Return any message whose release instant has already passed.
+            let released = matches!(
+                self.queued_messages.peek(),
+                Some(Reverse(scheduled)) if scheduled.release <= Instant::now()
+            );
+            if released {
+                let Reverse(scheduled) = self.queued_messages.pop().expect("just peeked");
+                self.record_release(&scheduled);
+                return Poll::Ready(Some(scheduled.message));
+            }
 
-        if self.next_slot.as_mut().poll(cx).is_ready() {
-            let current_slot = self.slot_clock.now().unwrap();
-            self.queue_slot_msgs(current_slot);
+            // Arm a wakeup for the earliest pending message in this slot, or
+            // clear any stale timer left over from a message already
+            // returned, so an elapsed `Sleep` is never polled again.
+            match self.queued_messages.peek() {
+                Some(Reverse(scheduled)) => {
+                    self.next_message = Some(Box::pin(sleep_until(scheduled.release)));
+                }
+                None => self.next_message = None,
+            }
 
-            let duration_to_next_slot = self.slot_clock.duration_to_next_slot().unwrap();
-            self.next_slot = Box::pin(sleep(duration_to_next_slot));
-            // We either have messages to return or need to poll the sleep
-            cx.waker().wake_by_ref();
-        }
+            let mut should_retry = false;
+            if let Some(next_message) = self.next_message.as_mut() {
+                if next_message.as_mut().poll(cx).is_ready() {
+                    should_retry = true;
+                }
+            }
+
+            if self.next_slot.as_mut().poll(cx).is_ready() {
+                let current_slot = self.slot_clock.now().unwrap();
+                let slot_start = Instant::now() - self.time_since_last_slot();
+                self.queue_slot_msgs(current_slot, slot_start);
 
-        Poll::Pending
+                let duration_to_next_slot = self.slot_clock.duration_to_next_slot().unwrap();
+                self.next_slot = Box::pin(sleep(duration_to_next_slot));
+                should_retry = true;
+            }
+
+            if !should_retry {
+                return Poll::Pending;
+            }
+        }
     }
 }
@@ -0,0 +1,25 @@
+//! Optional instrumentation for [`crate::Generator`].
+//!
+//! Metrics are recorded through a caller-supplied [`MetricsRecorder`] so this
+//! crate doesn't need to depend on any specific metrics library (e.g.
+//! `prometheus`); callers wire up their own recorder via
+//! [`crate::builder::GeneratorBuilder::with_metrics`].
+
+use crate::MsgType;
+
+/// Suggested bucket boundaries, in seconds, for the publish-delay histogram
+/// passed to [`MetricsRecorder::observe_publish_delay`].
+pub const DELAY_HISTOGRAM_BUCKETS: [f64; 11] =
+    [-4.0, -2.0, -1.0, -0.5, -0.1, 0.1, 0.5, 1.0, 2.0, 4.0, 8.0];
+
+/// Receives generation metrics from a [`crate::Generator`].
+pub trait MetricsRecorder: Send + Sync {
+    /// Called once for every message produced, with its `MsgType`.
+    fn inc_message_count(&self, msg_type: MsgType);
+
+    /// Called with the delay, in seconds, between a message's ideal
+    /// slot-relative publish time and the instant it was actually yielded
+    /// from `poll_next`. Negative values mean the message was yielded before
+    /// its ideal publish time (e.g. due to gossip clock-disparity jitter).
+    fn observe_publish_delay(&self, msg_type: MsgType, delay_seconds: f64);
+}
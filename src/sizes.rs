@@ -0,0 +1,30 @@
+//! Constants describing the shape of the gossip topics and duty committees
+//! that the generator simulates.
+//!
+//! These mirror the values defined by the consensus spec; they are kept here
+//! rather than inline so the selection logic in [`crate::slot_generator`] reads
+//! as spec references rather than magic numbers.
+
+/// Number of attestation subnets.
+pub const ATTESTATION_SUBNET_COUNT: u64 = 64;
+
+/// Number of sync committee subnets.
+pub const SYNC_COMMITTEE_SUBNET_COUNT: u64 = 4;
+
+/// Number of validators in the sync committee.
+pub const SYNC_COMMITTEE_SIZE: u64 = 512;
+
+/// Target number of aggregators per attestation committee.
+pub const TARGET_AGGREGATORS_PER_COMMITTEE: u64 = 16;
+
+/// Target number of aggregators per sync subcommittee.
+pub const TARGET_AGGREGATORS_PER_SYNC_SUBCOMMITTEE: u64 = 16;
+
+/// Number of slots in an epoch.
+pub const SLOTS_PER_EPOCH: u64 = 32;
+
+/// Number of blob sidecar subnets.
+pub const BLOB_SIDECAR_SUBNET_COUNT: u64 = 6;
+
+/// Default maximum number of blob sidecars generated per block.
+pub const DEFAULT_MAX_BLOBS_PER_BLOCK: u64 = 6;
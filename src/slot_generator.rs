@@ -0,0 +1,226 @@
+//! Deterministic duty selection for a simulated set of validators.
+//!
+//! `SlotGenerator` answers, for a given slot, which of the node's validators
+//! have which duties (proposing, attesting, aggregating, ...). Selection is
+//! derived from a hash of `(slot, validator)` rather than real BLS selection
+//! proofs, since this crate only needs realistic *shapes* of gossip traffic.
+
+use std::collections::hash_map::DefaultHasher;
+use std::collections::HashSet;
+use std::hash::{Hash, Hasher};
+
+use slot_clock::Slot;
+
+use crate::sizes::{
+    ATTESTATION_SUBNET_COUNT, BLOB_SIDECAR_SUBNET_COUNT, SLOTS_PER_EPOCH, SYNC_COMMITTEE_SIZE,
+    SYNC_COMMITTEE_SUBNET_COUNT, TARGET_AGGREGATORS_PER_COMMITTEE,
+    TARGET_AGGREGATORS_PER_SYNC_SUBCOMMITTEE,
+};
+
+/// Identifier of a validator known to this node.
+pub type ValId = u64;
+
+/// A gossip subnet id (attestation, sync-committee or blob sidecar).
+pub type Subnet = u64;
+
+/// Generates validator duties for a fixed-size simulated validator set.
+#[derive(Debug, Clone)]
+pub struct SlotGenerator {
+    /// Total number of validators in the simulated network (not just the
+    /// ones owned by this node).
+    validator_count: u64,
+    /// When `true`, aggregation duties are computed as if every owned
+    /// validator were operating inside a distributed validator (DVT)
+    /// cluster: see [`SlotGenerator::get_aggregates`] and
+    /// [`SlotGenerator::get_sync_committee_aggregates`].
+    distributed: bool,
+}
+
+impl SlotGenerator {
+    pub fn new(validator_count: u64, distributed: bool) -> Self {
+        Self {
+            validator_count,
+            distributed,
+        }
+    }
+
+    /// Returns the validators (from `validators`) that are due to propose a
+    /// `BeaconBlock` at `slot`. In practice this is at most one validator.
+    pub fn get_blocks(&self, slot: Slot, validators: &HashSet<ValId>) -> Vec<ValId> {
+        let proposer = self.hash(("proposer", slot.as_u64())) % self.validator_count;
+        validators
+            .iter()
+            .copied()
+            .filter(|v| *v == proposer)
+            .collect()
+    }
+
+    /// Returns `(attester, subnet)` pairs for every owned validator assigned
+    /// to attest at `slot`.
+    pub fn get_attestations(
+        &self,
+        slot: Slot,
+        validators: &HashSet<ValId>,
+    ) -> std::vec::IntoIter<(ValId, Subnet)> {
+        validators
+            .iter()
+            .map(|&v| (v, self.attestation_subnet(slot, v)))
+            .collect::<Vec<_>>()
+            .into_iter()
+    }
+
+    /// Returns `(aggregator, subnet)` pairs for owned validators selected as
+    /// aggregators at `slot`.
+    ///
+    /// In `distributed` mode the cluster cannot rely on any single node's
+    /// selection proof crossing the aggregator threshold, so every owned
+    /// validator assigned to its subnet this slot aggregates instead of only
+    /// the probabilistically-selected ones.
+    pub fn get_aggregates(
+        &self,
+        slot: Slot,
+        validators: &HashSet<ValId>,
+    ) -> std::vec::IntoIter<(ValId, Subnet)> {
+        validators
+            .iter()
+            .filter(|&&v| self.distributed || self.is_aggregator(slot, v))
+            .map(|&v| (v, self.attestation_subnet(slot, v)))
+            .collect::<Vec<_>>()
+            .into_iter()
+    }
+
+    /// Returns `(validator, subnet)` pairs for owned validators that are
+    /// members of the sync committee and must gossip a `SyncCommitteeMessage`
+    /// at `slot`.
+    pub fn get_sync_committee_messages(
+        &self,
+        slot: Slot,
+        validators: &HashSet<ValId>,
+    ) -> std::vec::IntoIter<(ValId, Subnet)> {
+        validators
+            .iter()
+            .filter(|&&v| self.is_sync_committee_member(v))
+            .map(|&v| (v, self.sync_committee_subnet(v)))
+            .collect::<Vec<_>>()
+            .into_iter()
+    }
+
+    /// Returns `(proposer, index, subnet)` triples for blob sidecars
+    /// accompanying the block at `slot`, when this node owns that slot's
+    /// proposer.
+    ///
+    /// The number of blobs included varies per block, like real traffic
+    /// (anywhere from none up to `max_blobs_per_block`), rather than always
+    /// maxing out the block.
+    pub fn get_blob_sidecars(
+        &self,
+        slot: Slot,
+        validators: &HashSet<ValId>,
+        max_blobs_per_block: u64,
+    ) -> Vec<(ValId, u64, Subnet)> {
+        self.get_blocks(slot, validators)
+            .into_iter()
+            .flat_map(|proposer| {
+                let blob_count = self.blob_count(slot, proposer, max_blobs_per_block);
+                (0..blob_count).map(move |index| {
+                    (proposer, index, index % BLOB_SIDECAR_SUBNET_COUNT)
+                })
+            })
+            .collect()
+    }
+
+    /// Deterministically varies the number of blobs included in the block
+    /// proposed by `proposer` at `slot`, between `0` and `max_blobs_per_block`
+    /// inclusive.
+    fn blob_count(&self, slot: Slot, proposer: ValId, max_blobs_per_block: u64) -> u64 {
+        self.hash(("blob_count", slot.as_u64(), proposer)) % (max_blobs_per_block + 1)
+    }
+
+    /// Returns `(validator, subnet)` pairs for owned sync committee members
+    /// selected as sync aggregators at `slot`.
+    ///
+    /// In `distributed` mode every sync committee member aggregates, rather
+    /// than only the probabilistically-selected ones; see
+    /// [`SlotGenerator::get_aggregates`]. Callers that also want the
+    /// one-slot-ahead lookahead distributed clients need should pass
+    /// `slot + 1`.
+    pub fn get_sync_committee_aggregates(
+        &self,
+        slot: Slot,
+        validators: &HashSet<ValId>,
+    ) -> std::vec::IntoIter<(ValId, Subnet)> {
+        validators
+            .iter()
+            .filter(|&&v| {
+                self.is_sync_committee_member(v)
+                    && (self.distributed || self.is_sync_aggregator(slot, v))
+            })
+            .map(|&v| (v, self.sync_committee_subnet(v)))
+            .collect::<Vec<_>>()
+            .into_iter()
+    }
+
+    /// Light client optimistic updates are published every slot by the node
+    /// that validated the head block; in this simulation that is always true.
+    pub fn should_emit_light_client_optimistic_update(&self, _slot: Slot) -> bool {
+        true
+    }
+
+    /// Light client finality updates are published once per epoch boundary,
+    /// by the node that produced that slot's block, unless `force` makes
+    /// this unconditional (e.g. simulating a node that also serves light
+    /// clients without having proposed).
+    pub fn should_emit_light_client_finality_update(
+        &self,
+        slot: Slot,
+        validators: &HashSet<ValId>,
+        force: bool,
+    ) -> bool {
+        slot.as_u64() % SLOTS_PER_EPOCH == 0
+            && (force || !self.get_blocks(slot, validators).is_empty())
+    }
+
+    fn attestation_subnet(&self, slot: Slot, validator: ValId) -> Subnet {
+        self.hash(("attestation_subnet", slot.as_u64(), validator)) % ATTESTATION_SUBNET_COUNT
+    }
+
+    fn sync_committee_subnet(&self, validator: ValId) -> Subnet {
+        self.hash(("sync_subnet", validator)) % SYNC_COMMITTEE_SUBNET_COUNT
+    }
+
+    fn is_aggregator(&self, slot: Slot, validator: ValId) -> bool {
+        // Roughly `TARGET_AGGREGATORS_PER_COMMITTEE` aggregators out of the
+        // committee's share of `validator_count` are selected.
+        let committee_size = self.validator_count / ATTESTATION_SUBNET_COUNT.max(1);
+        self.selected(
+            ("aggregator", slot.as_u64(), validator),
+            TARGET_AGGREGATORS_PER_COMMITTEE,
+            committee_size.max(1),
+        )
+    }
+
+    fn is_sync_committee_member(&self, validator: ValId) -> bool {
+        self.hash(("sync_member", validator)) % self.validator_count.max(1)
+            < SYNC_COMMITTEE_SIZE.min(self.validator_count)
+    }
+
+    fn is_sync_aggregator(&self, slot: Slot, validator: ValId) -> bool {
+        let subcommittee_size = SYNC_COMMITTEE_SIZE / SYNC_COMMITTEE_SUBNET_COUNT;
+        self.selected(
+            ("sync_aggregator", slot.as_u64(), validator),
+            TARGET_AGGREGATORS_PER_SYNC_SUBCOMMITTEE,
+            subcommittee_size,
+        )
+    }
+
+    /// Returns `true` for roughly `target / committee_size` of inputs.
+    fn selected(&self, seed: impl Hash, target: u64, committee_size: u64) -> bool {
+        self.hash(seed) % committee_size.max(1) < target.min(committee_size)
+    }
+
+    fn hash(&self, value: impl Hash) -> u64 {
+        let mut hasher = DefaultHasher::new();
+        value.hash(&mut hasher);
+        hasher.finish()
+    }
+}
@@ -0,0 +1,358 @@
+use std::collections::HashSet;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+use slot_clock::Slot;
+use tokio::time::Instant;
+
+use crate::metrics::MetricsRecorder;
+use crate::slot_generator::SlotGenerator;
+use crate::{Generator, Message, MsgType, ScheduledMessage};
+
+#[derive(Clone, Default)]
+struct RecordingMetrics {
+    counts: Arc<Mutex<Vec<MsgType>>>,
+    delays: Arc<Mutex<Vec<(MsgType, f64)>>>,
+}
+
+impl MetricsRecorder for RecordingMetrics {
+    fn inc_message_count(&self, msg_type: MsgType) {
+        self.counts.lock().unwrap().push(msg_type);
+    }
+
+    fn observe_publish_delay(&self, msg_type: MsgType, delay_seconds: f64) {
+        self.delays.lock().unwrap().push((msg_type, delay_seconds));
+    }
+}
+
+fn now() -> Duration {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .expect("system time is after the epoch")
+}
+
+#[tokio::test]
+async fn builder_builds_a_generator() {
+    let _generator = Generator::builder()
+        .genesis_time(now())
+        .validator_count(64)
+        .build();
+}
+
+#[tokio::test]
+async fn jitter_never_moves_a_message_before_the_previous_clock_boundary_minus_disparity() {
+    let disparity = crate::MAXIMUM_GOSSIP_CLOCK_DISPARITY;
+    let mut generator = Generator::builder()
+        .genesis_time(now())
+        .validator_count(64)
+        .gossip_clock_disparity(disparity)
+        .rng_seed(0)
+        .build();
+
+    let slot_start = Instant::now();
+    let release = slot_start + Duration::from_secs(4);
+    let floor = slot_start - disparity;
+    let ceiling = release + disparity;
+
+    for _ in 0..1_000 {
+        let jittered = generator.jitter(slot_start, release);
+        assert!(jittered >= floor, "{jittered:?} is before floor {floor:?}");
+        assert!(jittered <= ceiling, "{jittered:?} is after ceiling {ceiling:?}");
+    }
+}
+
+#[tokio::test]
+async fn jitter_is_a_no_op_without_configured_disparity() {
+    let mut generator = Generator::builder()
+        .genesis_time(now())
+        .validator_count(64)
+        .build();
+
+    let slot_start = Instant::now();
+    let release = slot_start + Duration::from_secs(4);
+    assert_eq!(generator.jitter(slot_start, release), release);
+}
+
+#[tokio::test(start_paused = true)]
+async fn poll_next_releases_messages_in_release_order_not_before_their_release_instant() {
+    use futures::StreamExt;
+
+    let mut generator = Generator::builder()
+        .genesis_time(now())
+        .validator_count(1)
+        .build();
+
+    let slot_start = Instant::now();
+    // Schedule out of order to confirm `poll_next` reorders by release
+    // instant, not insertion order.
+    generator.schedule(
+        slot_start,
+        slot_start + Duration::from_secs(8),
+        vec![Message::AggregateAndProofAttestation {
+            aggregator: 0,
+            subnet: 0,
+            slot: Slot::new(0),
+        }],
+    );
+    generator.schedule(
+        slot_start,
+        slot_start,
+        vec![Message::BeaconBlock {
+            proposer: 0,
+            slot: Slot::new(0),
+        }],
+    );
+    generator.schedule(
+        slot_start,
+        slot_start + Duration::from_secs(4),
+        vec![Message::Attestation {
+            attester: 0,
+            subnet: 0,
+            slot: Slot::new(0),
+        }],
+    );
+
+    let first = generator.next().await.expect("a message");
+    assert!(matches!(first, Message::BeaconBlock { .. }));
+    assert!(Instant::now() >= slot_start);
+
+    let second = generator.next().await.expect("a message");
+    assert!(matches!(second, Message::Attestation { .. }));
+    assert!(Instant::now() >= slot_start + Duration::from_secs(4));
+
+    let third = generator.next().await.expect("a message");
+    assert!(matches!(third, Message::AggregateAndProofAttestation { .. }));
+    assert!(Instant::now() >= slot_start + Duration::from_secs(8));
+}
+
+#[test]
+fn publish_offsets_spec_default_follows_the_thirds_of_slot_schedule() {
+    let slot_duration = Duration::from_secs(12);
+    let offsets = crate::PublishOffsets::spec_default(slot_duration);
+
+    assert_eq!(offsets.beacon_block, Duration::ZERO);
+    assert_eq!(offsets.blob_sidecar, Duration::ZERO);
+    assert_eq!(offsets.light_client_finality_update, Duration::ZERO);
+    assert_eq!(offsets.light_client_optimistic_update, Duration::ZERO);
+    assert_eq!(offsets.attestation, slot_duration / 3);
+    assert_eq!(offsets.sync_committee_message, slot_duration / 3);
+    assert_eq!(offsets.aggregate_and_proof_attestation, 2 * slot_duration / 3);
+    assert_eq!(offsets.signed_contribution_and_proof, 2 * slot_duration / 3);
+}
+
+#[test]
+fn blob_sidecar_count_varies_up_to_the_configured_max() {
+    let max_blobs_per_block = 6;
+    let proposer = 0;
+    let validators = HashSet::from([proposer]);
+    let slot_generator = SlotGenerator::new(1, false);
+
+    let mut observed_counts = HashSet::new();
+    for slot in 0..1_000 {
+        let sidecars =
+            slot_generator.get_blob_sidecars(Slot::new(slot), &validators, max_blobs_per_block);
+        assert!(sidecars.len() as u64 <= max_blobs_per_block);
+        for (_, index, subnet) in &sidecars {
+            assert_eq!(*subnet, *index % crate::sizes::BLOB_SIDECAR_SUBNET_COUNT);
+        }
+        observed_counts.insert(sidecars.len());
+    }
+
+    assert!(
+        observed_counts.len() > 1,
+        "expected blob sidecar count to vary across slots, got {observed_counts:?}"
+    );
+}
+
+#[test]
+fn light_client_optimistic_update_is_emitted_every_slot() {
+    let slot_generator = SlotGenerator::new(64, false);
+    for slot in 0..40 {
+        assert!(slot_generator.should_emit_light_client_optimistic_update(Slot::new(slot)));
+    }
+}
+
+#[test]
+fn light_client_finality_update_is_emitted_once_per_epoch_by_the_proposer() {
+    let slot_generator = SlotGenerator::new(1, false);
+    let owned_proposer = HashSet::from([0u64]);
+    let not_owned = HashSet::new();
+
+    for slot in 0..crate::sizes::SLOTS_PER_EPOCH {
+        let is_epoch_boundary = slot == 0;
+        assert_eq!(
+            slot_generator.should_emit_light_client_finality_update(
+                Slot::new(slot),
+                &owned_proposer,
+                false,
+            ),
+            is_epoch_boundary
+        );
+        // Without force and without owning the proposer, it's never emitted.
+        assert!(!slot_generator.should_emit_light_client_finality_update(
+            Slot::new(slot),
+            &not_owned,
+            false,
+        ));
+    }
+}
+
+#[test]
+fn light_client_finality_update_force_ignores_proposer_ownership() {
+    let slot_generator = SlotGenerator::new(1, false);
+    let not_owned = HashSet::new();
+
+    assert!(slot_generator.should_emit_light_client_finality_update(
+        Slot::new(0),
+        &not_owned,
+        true,
+    ));
+    assert!(!slot_generator.should_emit_light_client_finality_update(
+        Slot::new(1),
+        &not_owned,
+        true,
+    ));
+}
+
+#[test]
+fn distributed_mode_aggregates_every_assigned_validator() {
+    let validators: HashSet<u64> = (0..500).collect();
+    let slot = Slot::new(7);
+
+    let distributed = SlotGenerator::new(2048, true);
+    let distributed_count = distributed.get_aggregates(slot, &validators).count();
+    assert_eq!(distributed_count, validators.len());
+
+    let probabilistic = SlotGenerator::new(2048, false);
+    let probabilistic_count = probabilistic.get_aggregates(slot, &validators).count();
+    assert!(
+        probabilistic_count < distributed_count,
+        "expected probabilistic selection ({probabilistic_count}) to select fewer \
+         aggregators than distributed mode ({distributed_count})"
+    );
+}
+
+#[tokio::test]
+async fn distributed_mode_computes_sync_committee_contributions_one_slot_ahead() {
+    let current_slot = Slot::new(10);
+    let validators: HashSet<u64> = (0..500).collect();
+
+    let mut distributed = Generator::builder()
+        .genesis_time(now())
+        .validator_count(2048)
+        .validators(validators.clone())
+        .distributed(true)
+        .build();
+    distributed.queue_slot_msgs(current_slot, Instant::now());
+    let distributed_slots = contribution_slots(&distributed);
+    assert!(!distributed_slots.is_empty());
+    assert!(distributed_slots
+        .iter()
+        .all(|&slot| slot == current_slot.as_u64() + 1));
+
+    let mut non_distributed = Generator::builder()
+        .genesis_time(now())
+        .validator_count(2048)
+        .validators(validators)
+        .build();
+    non_distributed.queue_slot_msgs(current_slot, Instant::now());
+    let non_distributed_slots = contribution_slots(&non_distributed);
+    assert!(!non_distributed_slots.is_empty());
+    assert!(non_distributed_slots
+        .iter()
+        .all(|&slot| slot == current_slot.as_u64()));
+}
+
+fn beacon_block_scheduled_at(ideal_release: Instant) -> ScheduledMessage {
+    ScheduledMessage {
+        release: ideal_release,
+        ideal_release,
+        message: Message::BeaconBlock {
+            proposer: 0,
+            slot: Slot::new(0),
+        },
+    }
+}
+
+#[tokio::test]
+async fn record_release_reports_count_and_delay_to_the_configured_recorder() {
+    let metrics = RecordingMetrics::default();
+    let generator = Generator::builder()
+        .genesis_time(now())
+        .validator_count(64)
+        .with_metrics(metrics.clone())
+        .build();
+
+    generator.record_release(&beacon_block_scheduled_at(Instant::now()));
+
+    let counts = metrics.counts.lock().unwrap();
+    assert_eq!(counts.len(), 1);
+    assert!(matches!(counts[0], MsgType::BeaconBlock));
+
+    let delays = metrics.delays.lock().unwrap();
+    assert_eq!(delays.len(), 1);
+    assert!(matches!(delays[0].0, MsgType::BeaconBlock));
+    // The message's ideal release was just now, so the delay should be
+    // close to zero.
+    assert!(delays[0].1.abs() < 1.0, "delay was {}", delays[0].1);
+}
+
+#[tokio::test]
+async fn record_release_measures_delay_from_the_messages_own_ideal_release() {
+    // Regression test: the delay must be measured against the instant this
+    // specific message was supposed to be released, not re-derived from
+    // wherever the live slot clock happens to be when it's finally popped.
+    // A message that's been sitting in the queue for 9s because the
+    // consumer fell behind must report being ~9s late, never early.
+    let metrics = RecordingMetrics::default();
+    let generator = Generator::builder()
+        .genesis_time(now())
+        .validator_count(64)
+        .with_metrics(metrics.clone())
+        .build();
+
+    let ideal_release = Instant::now() - Duration::from_secs(9);
+    generator.record_release(&beacon_block_scheduled_at(ideal_release));
+
+    let delays = metrics.delays.lock().unwrap();
+    assert_eq!(delays.len(), 1);
+    assert!(delays[0].1 > 8.0, "delay was {}", delays[0].1);
+}
+
+#[tokio::test]
+async fn record_release_reports_a_negative_delay_when_yielded_early() {
+    let metrics = RecordingMetrics::default();
+    let generator = Generator::builder()
+        .genesis_time(now())
+        .validator_count(64)
+        .with_metrics(metrics.clone())
+        .build();
+
+    let ideal_release = Instant::now() + Duration::from_secs(2);
+    generator.record_release(&beacon_block_scheduled_at(ideal_release));
+
+    let delays = metrics.delays.lock().unwrap();
+    assert_eq!(delays.len(), 1);
+    assert!(delays[0].1 < -1.0, "delay was {}", delays[0].1);
+}
+
+#[tokio::test]
+async fn record_release_is_a_no_op_without_a_configured_recorder() {
+    let generator = Generator::builder()
+        .genesis_time(now())
+        .validator_count(64)
+        .build();
+
+    generator.record_release(&beacon_block_scheduled_at(Instant::now()));
+}
+
+fn contribution_slots(generator: &Generator) -> Vec<u64> {
+    generator
+        .queued_messages
+        .iter()
+        .filter_map(|scheduled| match &scheduled.0.message {
+            Message::SignedContributionAndProof { slot, .. } => Some(slot.as_u64()),
+            _ => None,
+        })
+        .collect()
+}